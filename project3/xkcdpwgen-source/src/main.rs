@@ -1,7 +1,11 @@
 extern crate rand;
+extern crate regex;
 use rand::{
     Rng,
-    seq::IteratorRandom};
+    RngCore,
+    SeedableRng,
+    rngs::{OsRng, StdRng}};
+use regex::Regex;
 use std::{
     env,
     fs,
@@ -22,17 +26,17 @@ fn main() {
         println!("Application error: {}", e);
         process::exit(1);
     }
-    
-} 
+
+}
 
 fn run(config: Config) -> Result<(), Box<dyn Error>> { // runs the password generator, with a given configuration
-    
+
     // help output, does not return anything besides help
     if config.help {
-        println!("usage: xkcdpwgen [-h] [-w WORDS] [-c CAPS] [-n NUMBERS] [-s SYMBOLS]
-                
+        println!("usage: xkcdpwgen [-h] [-w WORDS] [-c CAPS] [-n NUMBERS] [-s SYMBOLS] [--insecure]
+
                 Generate a secure, memorable password using the XKCD method
-                                
+
                 optional arguments:
                     -h, --help            show this help message and exit
                     -d, --debug           include debug info in the output
@@ -45,7 +49,23 @@ fn run(config: Config) -> Result<(), Box<dyn Error>> { // runs the password gene
                                           (default=0)
                     -s SYMBOLS, --symbols SYMBOLS
                                           insert SYMBOLS random symbols in the password
-                                          (default=0)");
+                                          (default=0)
+                    --insecure            draw from a fast, non-cryptographic PRNG instead of
+                                          the OS CSPRNG (testing only, never for real passwords)
+                    --entropy             print an entropy estimate (bits) and strength label
+                    -W PATH, --wordlist PATH
+                                          read candidate words from PATH instead of words.txt
+                                          (falls back to a built-in word list if unset and
+                                          words.txt cannot be opened)
+                    --require-all         regenerate until every requested character class
+                                          (lowercase, caps, numbers, symbols) appears at least once
+                    -l, --leet            apply a leetspeak substitution pass (a->@, i->!, o->0,
+                                          s->$, e->3) to each word, after capitalization
+                    --min-len LEN         only consider dictionary words at least LEN letters long
+                    --max-len LEN         only consider dictionary words at most LEN letters long
+                    --match REGEX         only consider dictionary words matching REGEX
+                    --seed SEED           seed a deterministic PRNG so the same config always
+                                          yields the same password (overrides --insecure)");
         return Ok(());
     }
 
@@ -56,21 +76,75 @@ fn run(config: Config) -> Result<(), Box<dyn Error>> { // runs the password gene
         println!("  caps: {}",  config.caps);
         println!("  nums: {}",  config.nums);
         println!("  syms: {}",  config.syms);
+        println!("  insecure: {}", config.insecure);
+        if let Some(seed) = config.seed {
+            println!("  seed: {}", seed);
+        }
     }
 
+    // single RNG for the whole run: OsRng by default, --seed for reproducible runs, --insecure
+    // only as a last resort for fast, non-cryptographic generation
+    let mut secure_rng = OsRng;
+    let mut fast_rng = rand::thread_rng();
+    let mut seeded_rng = StdRng::seed_from_u64(config.seed.unwrap_or(0));
+    let rng: &mut dyn RngCore = if config.seed.is_some() {
+        &mut seeded_rng
+    } else if config.insecure {
+        &mut fast_rng
+    } else {
+        &mut secure_rng
+    };
+
+    let dictionary = load_words(&config)?; // read the word list once; choose_word then just indexes into it
+
+    let mut password = generate_password(&config, &dictionary, rng);
+
+    // if the caller demanded every requested character class, regenerate until the distribution
+    // actually satisfies it, bounded so a pathological config can't loop forever
+    if config.require_all {
+        const MAX_ATTEMPTS: usize = 1000;
+        let mut attempts = 0;
+        while !CharDistro::count(&password.join("")).satisfies(&config) {
+            attempts = attempts + 1;
+            if attempts >= MAX_ATTEMPTS {
+                return Err(format!("could not satisfy --require-all after {} attempts", MAX_ATTEMPTS).into());
+            }
+            password = generate_password(&config, &dictionary, rng);
+        }
+    }
+
+    // entropy readout, behind --debug or the standalone --entropy flag
+    if config.debug || config.entropy {
+        let bits = shannon_entropy_bits(config.words, dictionary.len(), config.caps, config.nums, config.syms, SYMBOLS.len());
+        let label = if bits < 60.0 {
+            "weak"
+        } else if bits <= 80.0 {
+            "reasonable"
+        } else {
+            "strong"
+        };
+        println!("  entropy: {:.1} bits ({})", bits, label);
+    }
+
+    // print final password
+    println!("{}", password.join(""));
+    Ok(())
+}
+
+fn generate_password(config: &Config, dictionary: &[String], rng: &mut dyn RngCore) -> Vec<String> { // assembles one candidate password
     let total_capacity = config.words + config.nums + config.syms;
     let mut password = Vec::<String>::with_capacity(total_capacity);
 
-    // pick words from words.txt
+    // pick words from the dictionary
     for _ in 0..config.words {
-        password.push(choose_word());
+        password.push(choose_word(dictionary, rng));
     }
 
     // capitalize c random words
     let mut c = config.caps;
     let mut c_words: Vec<bool> = vec![false;config.words];
     while c > 0 {
-        let r = rand::thread_rng().gen_range(0..config.words);
+        let r = rng.gen_range(0..config.words);
         if c_words[r] { // if the word at r is already capitalized, generate a new r
             continue;
         }
@@ -79,34 +153,121 @@ fn run(config: Config) -> Result<(), Box<dyn Error>> { // runs the password gene
         c = c - 1;
     }
 
+    // leetspeak substitution, after capitalization so the case-sensitive mapping is predictable
+    if config.leet {
+        let table = Substitutions::default_table();
+        for word in password.iter_mut().take(config.words) {
+            *word = leetify(word.as_str(), &table);
+        }
+    }
+
     // insert n random digits
     let mut n = config.nums;
     while n > 0 {
-        let r = rand::thread_rng().gen_range(0..=password.len());
-        password.insert(r, String::from(rand_digit()));
+        let r = rng.gen_range(0..=password.len());
+        password.insert(r, String::from(rand_digit(rng)));
         n = n - 1;
     }
 
     // insert n random symbols
     let mut s = config.syms;
     while s > 0 {
-        let r = rand::thread_rng().gen_range(0..=password.len());
-        password.insert(r, String::from(rand_symbol()));
+        let r = rng.gen_range(0..=password.len());
+        password.insert(r, String::from(rand_symbol(rng)));
         s = s - 1;
     }
 
-    // print final password
-    println!("{}", password.join(""));
-    Ok(())
+    password
+}
+
+struct Substitutions(Vec<(char, char)>); // ordered character-substitution table for leetify
+
+impl Substitutions {
+    fn default_table() -> Substitutions { // the classic a->@, i->!, o->0, s->$, e->3 mapping
+        Substitutions(vec![('a', '@'), ('i', '!'), ('o', '0'), ('s', '$'), ('e', '3')])
+    }
+}
+
+fn leetify(word: &str, table: &Substitutions) -> String { // apply a leetspeak substitution table to a word
+    word.chars()
+        .map(|c| table.0.iter().find(|(from, _)| *from == c).map_or(c, |(_, to)| *to))
+        .collect()
+}
+
+struct CharDistro { // tallies which character classes are present in an assembled password
+    uppercase: usize,
+    lowercase: usize,
+    digit: usize,
+    special: usize,
+}
+
+impl CharDistro {
+    fn count(password: &str) -> CharDistro {
+        let mut uppercase = 0;
+        let mut lowercase = 0;
+        let mut digit = 0;
+        let mut special = 0;
+
+        for ch in password.chars() {
+            if ch.is_ascii_uppercase() {
+                uppercase = uppercase + 1;
+            } else if ch.is_ascii_lowercase() {
+                lowercase = lowercase + 1;
+            } else if ch.is_ascii_digit() {
+                digit = digit + 1;
+            } else {
+                special = special + 1;
+            }
+        }
+
+        CharDistro { uppercase, lowercase, digit, special }
+    }
+
+    // true once every class the config actually asked for is present at least once
+    fn satisfies(&self, config: &Config) -> bool {
+        (config.caps == 0 || self.uppercase > 0)
+            && (config.words == 0 || self.lowercase > 0)
+            && (config.nums == 0 || self.digit > 0)
+            && (config.syms == 0 || self.special > 0)
+    }
+}
+
+// Shannon entropy in bits of a password assembled from `words` dictionary words (chosen uniformly
+// from `dict_size` candidates), `caps` of which are capitalized, plus `nums` inserted digits and
+// `syms` inserted symbols (drawn from an alphabet of `symbol_set_len` characters).
+fn shannon_entropy_bits(words: usize, dict_size: usize, caps: usize, nums: usize, syms: usize, symbol_set_len: usize) -> f64 {
+    let word_bits = words as f64 * (dict_size as f64).log2();
+    let digit_bits = nums as f64 * (10_f64).log2();
+    let symbol_bits = syms as f64 * (symbol_set_len as f64).log2();
+    let cap_bits = log2_choose(words, caps);
+    word_bits + digit_bits + symbol_bits + cap_bits
+}
+
+// log2(C(n, k)), i.e. the bits of entropy in choosing which k of n words are capitalized
+fn log2_choose(n: usize, k: usize) -> f64 {
+    let mut bits = 0.0;
+    for i in 0..k {
+        bits += ((n - i) as f64).log2() - ((i + 1) as f64).log2();
+    }
+    bits
 }
 
 struct Config { // stores configuration data
-    words: usize,  // number of words
-    caps: usize,   // number of capitalized words
-    nums: usize,   // number of inserted digits
-    syms: usize,   // number of inserted symbols
-    help: bool,    // help option
-    debug: bool,   // debug option 
+    words: usize,    // number of words
+    caps: usize,     // number of capitalized words
+    nums: usize,     // number of inserted digits
+    syms: usize,     // number of inserted symbols
+    help: bool,      // help option
+    debug: bool,     // debug option
+    insecure: bool,  // opt back into a fast, non-cryptographic PRNG
+    entropy: bool,   // print the entropy estimate without full --debug output
+    wordlist: Option<String>, // path to a user-supplied word list, overriding words.txt
+    require_all: bool, // regenerate until every requested character class is present
+    leet: bool, // apply a leetspeak substitution pass to each word
+    min_len: Option<usize>, // only consider dictionary words at least this long
+    max_len: Option<usize>, // only consider dictionary words at most this long
+    pattern: Option<String>, // only consider dictionary words matching this regex
+    seed: Option<u64>, // deterministic seed for reproducible generation
 }
 
 impl Config { // config implementations
@@ -117,18 +278,27 @@ impl Config { // config implementations
         let mut syms = 0;
         let mut help = false;
         let mut debug = false;
-        
+        let mut insecure = false;
+        let mut entropy = false;
+        let mut wordlist = None;
+        let mut require_all = false;
+        let mut leet = false;
+        let mut min_len = None;
+        let mut max_len = None;
+        let mut pattern = None;
+        let mut seed = None;
+
         let n = args.len(); // number of arguments
 
         if n == 1 { // no extra args, default config
-            return Ok(Config { words, caps, nums, syms, help, debug });
+            return Ok(Config { words, caps, nums, syms, help, debug, insecure, entropy, wordlist, require_all, leet, min_len, max_len, pattern, seed });
         }
 
         let mut i = 1; // loop variable
         while i < n { // iterate over extra args (range is not inclusive, so goes from a[1] to a[n-1])
 
             // words case
-            if args[i] == "-w" || args[i] == "--words" { 
+            if args[i] == "-w" || args[i] == "--words" {
                 if (i+1) < n { // check if we can access a[i+1], and that it is a valid argument
                     if let Ok(w) = args[i+1].parse::<usize>() {
                         words = w;
@@ -142,7 +312,7 @@ impl Config { // config implementations
             }
 
             // caps case
-            else if args[i] == "-c" || args[i] == "--caps" { 
+            else if args[i] == "-c" || args[i] == "--caps" {
                 if (i+1) < n { // check if we can access a[i+1], and that it is a valid argument
                     if let Ok(c) = args[i+1].parse::<usize>() {
                         caps = cmp::min(words, c);
@@ -156,7 +326,7 @@ impl Config { // config implementations
             }
 
             // nums case
-            else if args[i] == "-n" || args[i] == "--numbers" { 
+            else if args[i] == "-n" || args[i] == "--numbers" {
                 if (i+1) < n { // check if we can access a[i+1], and that it is a valid argument
                     if let Ok(n_) = args[i+1].parse::<usize>() {
                         nums = n_;
@@ -170,7 +340,7 @@ impl Config { // config implementations
             }
 
             // syms case
-            else if args[i] == "-s" || args[i] == "--symbols" { 
+            else if args[i] == "-s" || args[i] == "--symbols" {
                 if (i+1) < n { // check if we can access a[i+1], and that it is a valid argument
                     if let Ok(s) = args[i+1].parse::<usize>() {
                         syms = s;
@@ -194,33 +364,147 @@ impl Config { // config implementations
                 debug = true;
                 i = i + 1;
             }
-            
+
+            // insecure case
+            else if args[i] == "--insecure" {
+                insecure = true;
+                i = i + 1;
+            }
+
+            // entropy case
+            else if args[i] == "--entropy" {
+                entropy = true;
+                i = i + 1;
+            }
+
+            // wordlist case
+            else if args[i] == "-W" || args[i] == "--wordlist" {
+                if (i+1) < n { // check if we can access a[i+1]
+                    wordlist = Some(args[i+1].clone());
+                    i = i + 2;
+                } else {
+                    return Err("no parameter for option -W");
+                }
+            }
+
+            // require-all case
+            else if args[i] == "--require-all" {
+                require_all = true;
+                i = i + 1;
+            }
+
+            // leet case
+            else if args[i] == "-l" || args[i] == "--leet" {
+                leet = true;
+                i = i + 1;
+            }
+
+            // min-len case
+            else if args[i] == "--min-len" {
+                if (i+1) < n {
+                    if let Ok(m) = args[i+1].parse::<usize>() {
+                        min_len = Some(m);
+                        i = i + 2;
+                    } else {
+                        return Err("invalid parameter for option --min-len");
+                    }
+                } else {
+                    return Err("no parameter for option --min-len");
+                }
+            }
+
+            // max-len case
+            else if args[i] == "--max-len" {
+                if (i+1) < n {
+                    if let Ok(m) = args[i+1].parse::<usize>() {
+                        max_len = Some(m);
+                        i = i + 2;
+                    } else {
+                        return Err("invalid parameter for option --max-len");
+                    }
+                } else {
+                    return Err("no parameter for option --max-len");
+                }
+            }
+
+            // match case
+            else if args[i] == "--match" {
+                if (i+1) < n {
+                    pattern = Some(args[i+1].clone());
+                    i = i + 2;
+                } else {
+                    return Err("no parameter for option --match");
+                }
+            }
+
+            // seed case
+            else if args[i] == "--seed" {
+                if (i+1) < n {
+                    if let Ok(s) = args[i+1].parse::<u64>() {
+                        seed = Some(s);
+                        i = i + 2;
+                    } else {
+                        return Err("invalid parameter for option --seed");
+                    }
+                } else {
+                    return Err("no parameter for option --seed");
+                }
+            }
+
             // invalid arg case
             else {
                 println!("invalid arg: {}", args[i]);
                 return Err("invalid argument");
             }
         }
-        
-        Ok(Config { words, caps, nums, syms, help, debug })
+
+        Ok(Config { words, caps, nums, syms, help, debug, insecure, entropy, wordlist, require_all, leet, min_len, max_len, pattern, seed })
     }
 }
 
-fn choose_word() -> String { // choose random word from words.txt
-    const FILENAME: &str = "words.txt";
+const WORDS_FILENAME: &str = "words.txt";
+const SYMBOLS: &str = "~!@#$%^&*.:;";
+const DEFAULT_WORDS: &str = include_str!("default_words.txt"); // built-in fallback, used when no words.txt is found
 
-    let f = fs::File::open(FILENAME)
-        .unwrap_or_else(|err| {
-            println!("Problem reading {}: {}", FILENAME, err);
-            process::exit(1);
-        });
-    let f = BufReader::new(f);
+fn load_words(config: &Config) -> Result<Vec<String>, Box<dyn Error>> { // read the whole dictionary once, up front
+    let path = config.wordlist.as_deref().unwrap_or(WORDS_FILENAME);
 
-    let lines = f.lines().map(|l| l.expect("Couldn't read line"));
+    let words: Vec<String> = match fs::File::open(path) {
+        Ok(f) => BufReader::new(f).lines().map(|l| l.map_err(|e| e.into())).collect::<Result<_, Box<dyn Error>>>()?,
+        Err(e) => {
+            if config.wordlist.is_none() { // no explicit --wordlist was given, fall back to the built-in list
+                DEFAULT_WORDS.lines().map(String::from).collect()
+            } else {
+                return Err(format!("Problem reading {}: {}", path, e).into());
+            }
+        }
+    };
 
-    lines
-        .choose(&mut rand::thread_rng())
-        .expect("File has no lines")
+    filter_words(words, config)
+}
+
+fn filter_words(words: Vec<String>, config: &Config) -> Result<Vec<String>, Box<dyn Error>> { // apply --min-len/--max-len/--match once, at load time
+    let pattern = match &config.pattern {
+        Some(p) => Some(Regex::new(p)?),
+        None => None,
+    };
+
+    let filtered: Vec<String> = words.into_iter()
+        .filter(|w| config.min_len.is_none_or(|m| w.len() >= m))
+        .filter(|w| config.max_len.is_none_or(|m| w.len() <= m))
+        .filter(|w| pattern.as_ref().is_none_or(|r| r.is_match(w)))
+        .collect();
+
+    if filtered.is_empty() {
+        return Err("no dictionary words satisfy the --min-len/--max-len/--match filters".into());
+    }
+
+    Ok(filtered)
+}
+
+fn choose_word(dictionary: &[String], rng: &mut dyn RngCore) -> String { // choose random word from the cached dictionary
+    let r = rng.gen_range(0..dictionary.len());
+    dictionary[r].clone()
 }
 
 fn capitalize(s: &str) -> String {
@@ -231,11 +515,106 @@ fn capitalize(s: &str) -> String {
     }
 }
 
-fn rand_digit() -> char { // returns a random digit
-    char::from_digit(rand::thread_rng().gen_range(0..10), 10).unwrap()
+fn rand_digit(rng: &mut dyn RngCore) -> char { // returns a random digit
+    char::from_digit(rng.gen_range(0..10), 10).unwrap()
+}
+
+fn rand_symbol(rng: &mut dyn RngCore) -> char { // returns a random symbol
+    SYMBOLS.chars().nth(rng.gen_range(0..SYMBOLS.len())).unwrap()
+}
+
+#[cfg(test)]
+impl Config { // a default config for tests to override fields on, mirroring Config::new's defaults
+    fn default_for_test() -> Config {
+        Config {
+            words: 4, caps: 0, nums: 0, syms: 0,
+            help: false, debug: false, insecure: false, entropy: false,
+            wordlist: None, require_all: false, leet: false,
+            min_len: None, max_len: None, pattern: None, seed: None,
+        }
+    }
 }
 
-fn rand_symbol() -> char { // returns a random symbol
-    let symbols = String::from("~!@#$%^&*.:;");
-    symbols.chars().nth(rand::thread_rng().gen_range(0..symbols.len())).unwrap()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log2_choose_matches_known_values() {
+        assert_eq!(log2_choose(4, 0), 0.0);
+        assert!((log2_choose(4, 2) - 6_f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn shannon_entropy_bits_sums_each_source() {
+        // 4 words from a 2048-word dictionary, no caps/nums/syms: 4 * log2(2048) = 44 bits exactly
+        let bits = shannon_entropy_bits(4, 2048, 0, 0, 0, 12);
+        assert!((bits - 44.0).abs() < 1e-9);
+
+        // adding 2 digits and 1 symbol from a 12-symbol set adds 2 * log2(10) + log2(12) bits
+        let bits = shannon_entropy_bits(4, 2048, 0, 2, 1, 12);
+        assert!((bits - (44.0 + 100_f64.log2() + 12_f64.log2())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn shannon_entropy_bits_includes_cap_placement() {
+        let without_caps = shannon_entropy_bits(4, 2048, 0, 0, 0, 12);
+        let with_caps = shannon_entropy_bits(4, 2048, 2, 0, 0, 12);
+        assert!((with_caps - without_caps - log2_choose(4, 2)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn leetify_applies_default_substitutions() {
+        let table = Substitutions::default_table();
+        assert_eq!(leetify("apple", &table), "@ppl3");
+        assert_eq!(leetify("xyz", &table), "xyz");
+    }
+
+    #[test]
+    fn char_distro_counts_each_class() {
+        let d = CharDistro::count("Ab1!");
+        assert_eq!(d.uppercase, 1);
+        assert_eq!(d.lowercase, 1);
+        assert_eq!(d.digit, 1);
+        assert_eq!(d.special, 1);
+    }
+
+    #[test]
+    fn char_distro_satisfies_only_requested_classes() {
+        let mut config = Config::default_for_test();
+        config.caps = 1;
+        config.nums = 1;
+        config.syms = 0;
+
+        assert!(!CharDistro::count("applebravo1").satisfies(&config)); // no capitalized word
+        assert!(CharDistro::count("Applebravo1").satisfies(&config));
+    }
+
+    #[test]
+    fn char_distro_satisfies_empty_password_when_no_words_requested() {
+        let mut config = Config::default_for_test();
+        config.words = 0;
+        assert!(CharDistro::count("").satisfies(&config));
+    }
+
+    #[test]
+    fn filter_words_applies_length_and_regex_filters() {
+        let words = vec!["a".to_string(), "apple".to_string(), "Banana".to_string(), "kiwi".to_string()];
+        let mut config = Config::default_for_test();
+        config.min_len = Some(4);
+        config.max_len = Some(5);
+        config.pattern = Some("^[a-z]+$".to_string());
+
+        let filtered = filter_words(words, &config).unwrap();
+        assert_eq!(filtered, vec!["apple".to_string(), "kiwi".to_string()]);
+    }
+
+    #[test]
+    fn filter_words_errors_when_nothing_matches() {
+        let words = vec!["apple".to_string()];
+        let mut config = Config::default_for_test();
+        config.min_len = Some(100);
+
+        assert!(filter_words(words, &config).is_err());
+    }
 }